@@ -4,20 +4,698 @@ use crate::render::{RenderBackend, RenderParams, RenderPipelineHandle};
 use gfx_hal::{
     self as hal,
     adapter::{Adapter, PhysicalDevice},
+    buffer,
+    command::CommandBuffer,
     device::Device,
-    format::{ChannelType, Format},
-    image::{Extent as Extent3D, Layout as ImageLayout},
+    format::{Aspects, ChannelType, Format, Swizzle},
+    image::{
+        Extent as Extent3D, Kind as ImageKind, Layout as ImageLayout, Offset as ImageOffset,
+        SamplerDesc, SubresourceLayers, SubresourceRange, Tiling as ImageTiling,
+        Usage as ImageUsage, ViewKind,
+    },
+    memory,
     pass::{self, Subpass}, pool,
-    queue::{QueueFamily, family::QueueGroup},
+    queue::{CommandQueue, QueueFamily, family::QueueGroup},
     window::{Extent2D, PresentationSurface, Surface, SwapchainConfig},
     Instance,
     pso,
 };
 use gfx_auxil::read_spirv;
+use std::collections::HashMap;
 use std::iter;
 use std::io::Cursor;
 use std::sync::Mutex;
 
+/// Max pipelines a single `GfxBackend` can host (bounds the iChannel descriptor pool size).
+const MAX_PIPELINES: usize = 64;
+
+/// Number of Shadertoy `iChannel` sampled-texture inputs per pipeline.
+const CHANNEL_COUNT: usize = 4;
+
+/// Directory holding the content-addressed shader cache and the merged pipeline cache.
+const SHADER_CACHE_DIR: &str = "shader_cache";
+
+fn shader_cache_dir() -> &'static std::path::Path {
+    std::path::Path::new(SHADER_CACHE_DIR)
+}
+
+/// `Named` matches case-insensitively against a substring of `AdapterInfo::name`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdapterPreference {
+    PreferDiscrete,
+    PreferIntegrated,
+    LowPower,
+    Named(String),
+}
+
+impl Default for AdapterPreference {
+    fn default() -> Self {
+        AdapterPreference::PreferDiscrete
+    }
+}
+
+/// Higher is better; device type gives a baseline ordering, `preference` adds a bonus on a match.
+fn score_adapter<B: hal::Backend>(adapter: &Adapter<B>, preference: &AdapterPreference) -> i32 {
+    use hal::adapter::DeviceType;
+
+    let mut score = match adapter.info.device_type {
+        DeviceType::DiscreteGpu => 30,
+        DeviceType::IntegratedGpu => 20,
+        DeviceType::VirtualGpu => 10,
+        DeviceType::Cpu => 1,
+        DeviceType::Other => 0,
+    };
+
+    let matches_preference = match preference {
+        AdapterPreference::PreferDiscrete => adapter.info.device_type == DeviceType::DiscreteGpu,
+        AdapterPreference::PreferIntegrated | AdapterPreference::LowPower => {
+            adapter.info.device_type == DeviceType::IntegratedGpu
+        }
+        AdapterPreference::Named(substr) => adapter
+            .info
+            .name
+            .to_lowercase()
+            .contains(&substr.to_lowercase()),
+    };
+    if matches_preference {
+        score += 1000;
+    }
+
+    score
+}
+
+/// Picks the best-scoring adapter that can both render and present to `surface`.
+fn select_adapter<B: hal::Backend>(
+    adapters: Vec<Adapter<B>>,
+    surface: &B::Surface,
+    preference: &AdapterPreference,
+) -> Option<Adapter<B>> {
+    adapters
+        .into_iter()
+        .filter(|adapter| {
+            adapter.queue_families.iter().any(|family| {
+                surface.supports_queue_family(family) && family.queue_type().supports_graphics()
+            })
+        })
+        .max_by_key(|adapter| score_adapter(adapter, preference))
+}
+
+/// Stable key for the adapter a pipeline cache was built against.
+fn device_cache_key(adapter_info: &hal::adapter::AdapterInfo) -> String {
+    format!("{:04x}:{:04x}:{}", adapter_info.vendor, adapter_info.device, adapter_info.name)
+}
+
+fn shader_content_hash(shader_source: &str, device_key: &str) -> String {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    shader_source.hash(&mut hasher);
+    device_key.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Discards the cached blob if it wasn't written by the same adapter/driver.
+fn load_pipeline_cache_data(device_key: &str) -> Option<Vec<u8>> {
+    let stored_key = std::fs::read_to_string(shader_cache_dir().join("pipeline.cache.meta")).ok()?;
+    if stored_key.trim() != device_key {
+        return None;
+    }
+    std::fs::read(shader_cache_dir().join("pipeline.cache")).ok()
+}
+
+/// Color format of every offscreen Buffer A-D target; high precision for feedback effects.
+const OFFSCREEN_FORMAT: Format = Format::Rgba32Sfloat;
+
+/// Composites the two eye targets of [`OutputMode::Stereo`] side-by-side into the swapchain image.
+const STEREO_RESOLVE_SHADER: &str = "\
+#version 450
+layout(set = 0, binding = 0) uniform sampler2D iChannel0;
+layout(set = 0, binding = 1) uniform sampler2D iChannel1;
+layout(location = 0) out vec4 fragColor;
+void main() {
+    vec2 uv = gl_FragCoord.xy / iResolution.xy;
+    if (uv.x < 0.5) {
+        fragColor = texture(iChannel0, vec2(uv.x * 2.0, uv.y));
+    } else {
+        fragColor = texture(iChannel1, vec2(uv.x * 2.0 - 1.0, uv.y));
+    }
+}
+";
+
+/// Handle to an offscreen buffer pass added with `GfxBackend::add_buffer_pass`.
+pub type BufferPassHandle = usize;
+
+/// Where an `iChannel` binding's data comes from.
+#[derive(Clone, Copy)]
+pub enum ChannelSource {
+    /// A texture uploaded with `upload_channel_texture`.
+    Texture(usize),
+    /// A buffer pass's most recently completed output.
+    BufferPass(BufferPassHandle),
+}
+
+/// Edge behavior for an `iChannel` sampler, mirroring Shadertoy's texture input settings.
+#[derive(Clone, Copy, Debug)]
+pub enum TextureWrap {
+    Repeat,
+    ClampToEdge,
+    MirrorRepeat,
+}
+
+impl TextureWrap {
+    fn to_hal(self) -> hal::image::WrapMode {
+        match self {
+            TextureWrap::Repeat => hal::image::WrapMode::Tile,
+            TextureWrap::ClampToEdge => hal::image::WrapMode::Clamp,
+            TextureWrap::MirrorRepeat => hal::image::WrapMode::Mirror,
+        }
+    }
+}
+
+/// Filtering for an `iChannel` sampler, mirroring Shadertoy's texture input settings.
+#[derive(Clone, Copy, Debug)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+}
+
+impl TextureFilter {
+    fn to_hal(self) -> hal::image::Filter {
+        match self {
+            TextureFilter::Nearest => hal::image::Filter::Nearest,
+            TextureFilter::Linear => hal::image::Filter::Linear,
+        }
+    }
+}
+
+/// A decoded iChannel input: a sampled image plus the sampler it's bound with.
+struct GfxTexture<B: gfx_hal::Backend> {
+    image: B::Image,
+    memory: B::Memory,
+    view: B::ImageView,
+    sampler: B::Sampler,
+}
+
+/// One side of a buffer pass's double-buffered offscreen color target.
+struct OffscreenTarget<B: gfx_hal::Backend> {
+    image: B::Image,
+    memory: B::Memory,
+    view: B::ImageView,
+}
+
+/// An offscreen Buffer A-D pass, ping-ponging between two `OFFSCREEN_FORMAT` targets.
+struct BufferPass<B: gfx_hal::Backend> {
+    render_pass: B::RenderPass,
+    framebuffer: B::Framebuffer,
+    pipeline: B::GraphicsPipeline,
+    descriptor_set: B::DescriptorSet,
+    targets: [OffscreenTarget<B>; 2],
+    /// Index into `targets` rendered into this frame; `1 - write_index` is last frame's output.
+    write_index: usize,
+    channels: [Option<ChannelSource>; CHANNEL_COUNT],
+    extent: Extent2D,
+}
+
+impl<B: gfx_hal::Backend> BufferPass<B> {
+    fn read_index(&self) -> usize {
+        1 - self.write_index
+    }
+}
+
+/// Output mode for the final Image pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    Mono,
+    /// `eye_separation` is the per-eye camera offset along X.
+    Stereo { eye_separation: f32 },
+}
+
+impl Default for OutputMode {
+    fn default() -> Self {
+        OutputMode::Mono
+    }
+}
+
+/// Resources for [`OutputMode::Stereo`]: a single color image with one array layer per eye,
+/// composited side-by-side into the swapchain image by a small built-in resolve pass.
+struct StereoState<B: gfx_hal::Backend> {
+    render_pass: B::RenderPass,
+    framebuffer: B::Framebuffer,
+    image: B::Image,
+    memory: B::Memory,
+    /// One single-layer view per eye (`[0]` = left, `[1]` = right).
+    layer_views: [B::ImageView; 2],
+    resolve_pipeline: B::GraphicsPipeline,
+    resolve_descriptor_set: B::DescriptorSet,
+    extent: Extent2D,
+}
+
+/// Allocates a device-local, sampled color-attachment image for an offscreen buffer pass target.
+fn create_offscreen_target<B: gfx_hal::Backend>(
+    device: &B::Device,
+    physical_device: &B::PhysicalDevice,
+    extent: Extent2D,
+) -> OffscreenTarget<B> {
+    let mut image = unsafe {
+        device.create_image(
+            ImageKind::D2(extent.width, extent.height, 1, 1),
+            1,
+            OFFSCREEN_FORMAT,
+            ImageTiling::Optimal,
+            ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+            memory::SparseFlags::empty(),
+            hal::image::ViewCapabilities::empty(),
+        )
+    }
+    .unwrap();
+
+    let image_req = unsafe { device.get_image_requirements(&image) };
+    let memory_type = physical_device
+        .memory_properties()
+        .memory_types
+        .iter()
+        .enumerate()
+        .position(|(id, mem_type)| {
+            image_req.type_mask & (1 << id) != 0
+                && mem_type.properties.contains(memory::Properties::DEVICE_LOCAL)
+        })
+        .unwrap()
+        .into();
+    let memory = unsafe { device.allocate_memory(memory_type, image_req.size) }.unwrap();
+    unsafe { device.bind_image_memory(&memory, 0, &mut image) }.unwrap();
+
+    let view = unsafe {
+        device.create_image_view(
+            &image,
+            ViewKind::D2,
+            OFFSCREEN_FORMAT,
+            Swizzle::NO_SWIZZLE,
+            ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+            SubresourceRange {
+                aspects: Aspects::COLOR,
+                ..Default::default()
+            },
+        )
+    }
+    .unwrap();
+
+    OffscreenTarget { image, memory, view }
+}
+
+/// Uploads `pixels` (tightly packed RGBA8) as a sampled, device-local texture. Blocks until done.
+fn create_texture<B: gfx_hal::Backend>(
+    device: &B::Device,
+    physical_device: &B::PhysicalDevice,
+    command_pool: &mut B::CommandPool,
+    queue: &mut B::CommandQueue,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    wrap: TextureWrap,
+    filter: TextureFilter,
+) -> GfxTexture<B> {
+    let format = Format::Rgba8Srgb;
+    let memory_types = physical_device.memory_properties().memory_types;
+
+    let mut image = unsafe {
+        device.create_image(
+            ImageKind::D2(width, height, 1, 1),
+            1,
+            format,
+            ImageTiling::Optimal,
+            ImageUsage::SAMPLED | ImageUsage::TRANSFER_DST,
+            memory::SparseFlags::empty(),
+            hal::image::ViewCapabilities::empty(),
+        )
+    }
+    .unwrap();
+
+    let image_req = unsafe { device.get_image_requirements(&image) };
+    let image_memory_type = memory_types
+        .iter()
+        .enumerate()
+        .position(|(id, mem_type)| {
+            image_req.type_mask & (1 << id) != 0
+                && mem_type.properties.contains(memory::Properties::DEVICE_LOCAL)
+        })
+        .unwrap()
+        .into();
+    let image_memory = unsafe { device.allocate_memory(image_memory_type, image_req.size) }.unwrap();
+    unsafe { device.bind_image_memory(&image_memory, 0, &mut image) }.unwrap();
+
+    let upload_size = (width * height * 4) as u64;
+    let mut staging_buffer =
+        unsafe { device.create_buffer(upload_size, buffer::Usage::TRANSFER_SRC, memory::SparseFlags::empty()) }
+            .unwrap();
+    let buffer_req = unsafe { device.get_buffer_requirements(&staging_buffer) };
+    let staging_memory_type = memory_types
+        .iter()
+        .enumerate()
+        .position(|(id, mem_type)| {
+            buffer_req.type_mask & (1 << id) != 0
+                && mem_type.properties.contains(memory::Properties::CPU_VISIBLE)
+        })
+        .unwrap()
+        .into();
+    let staging_memory =
+        unsafe { device.allocate_memory(staging_memory_type, buffer_req.size) }.unwrap();
+    unsafe { device.bind_buffer_memory(&staging_memory, 0, &mut staging_buffer) }.unwrap();
+
+    unsafe {
+        let mapping = device.map_memory(&staging_memory, memory::Segment::ALL).unwrap();
+        std::ptr::copy_nonoverlapping(pixels.as_ptr(), mapping, pixels.len());
+        device
+            .flush_mapped_memory_ranges(iter::once((&staging_memory, memory::Segment::ALL)))
+            .unwrap();
+        device.unmap_memory(&staging_memory);
+    }
+
+    let color_range = SubresourceRange {
+        aspects: Aspects::COLOR,
+        ..Default::default()
+    };
+
+    let mut command_buffer = unsafe { command_pool.allocate_one(hal::command::Level::Primary) };
+    unsafe {
+        command_buffer.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
+
+        command_buffer.pipeline_barrier(
+            pso::PipelineStage::TOP_OF_PIPE..pso::PipelineStage::TRANSFER,
+            memory::Dependencies::empty(),
+            iter::once(memory::Barrier::Image {
+                states: (hal::image::Access::empty(), ImageLayout::Undefined)
+                    ..(hal::image::Access::TRANSFER_WRITE, ImageLayout::TransferDstOptimal),
+                target: &image,
+                families: None,
+                range: color_range,
+            }),
+        );
+
+        command_buffer.copy_buffer_to_image(
+            &staging_buffer,
+            &image,
+            ImageLayout::TransferDstOptimal,
+            iter::once(hal::command::BufferImageCopy {
+                buffer_offset: 0,
+                buffer_width: width,
+                buffer_height: height,
+                image_layers: SubresourceLayers {
+                    aspects: Aspects::COLOR,
+                    level: 0,
+                    layers: 0..1,
+                },
+                image_offset: ImageOffset { x: 0, y: 0, z: 0 },
+                image_extent: Extent3D { width, height, depth: 1 },
+            }),
+        );
+
+        command_buffer.pipeline_barrier(
+            pso::PipelineStage::TRANSFER..pso::PipelineStage::FRAGMENT_SHADER,
+            memory::Dependencies::empty(),
+            iter::once(memory::Barrier::Image {
+                states: (hal::image::Access::TRANSFER_WRITE, ImageLayout::TransferDstOptimal)
+                    ..(hal::image::Access::SHADER_READ, ImageLayout::ShaderReadOnlyOptimal),
+                target: &image,
+                families: None,
+                range: color_range,
+            }),
+        );
+
+        command_buffer.finish();
+    }
+
+    let mut upload_fence = device.create_fence(false).unwrap();
+    unsafe {
+        queue.submit(iter::once(&command_buffer), iter::empty(), iter::empty(), Some(&mut upload_fence));
+        device.wait_for_fence(&upload_fence, !0).unwrap();
+        device.destroy_fence(upload_fence);
+        device.free_memory(staging_memory);
+        device.destroy_buffer(staging_buffer);
+    }
+
+    let view = unsafe {
+        device.create_image_view(
+            &image,
+            ViewKind::D2,
+            format,
+            Swizzle::NO_SWIZZLE,
+            ImageUsage::SAMPLED,
+            color_range,
+        )
+    }
+    .unwrap();
+
+    let sampler = unsafe { device.create_sampler(&SamplerDesc::new(filter.to_hal(), wrap.to_hal())) }.unwrap();
+
+    GfxTexture { image, memory: image_memory, view, sampler }
+}
+
+/// Writes `texture` into `descriptor_set`'s `iChannelN` binding.
+fn write_channel_descriptor<B: gfx_hal::Backend>(
+    device: &B::Device,
+    descriptor_set: &mut B::DescriptorSet,
+    binding: u32,
+    texture: &GfxTexture<B>,
+) {
+    unsafe {
+        device.write_descriptor_sets(iter::once(pso::DescriptorSetWrite {
+            set: descriptor_set,
+            binding,
+            array_offset: 0,
+            descriptors: iter::once(pso::Descriptor::CombinedImageSampler(
+                &texture.view,
+                ImageLayout::ShaderReadOnlyOptimal,
+                &texture.sampler,
+            )),
+        }));
+    }
+}
+
+/// Writes an arbitrary image view into `descriptor_set`'s `iChannelN` binding.
+fn write_descriptor_set_image<B: gfx_hal::Backend>(
+    device: &B::Device,
+    descriptor_set: &mut B::DescriptorSet,
+    binding: u32,
+    view: &B::ImageView,
+    sampler: &B::Sampler,
+) {
+    unsafe {
+        device.write_descriptor_sets(iter::once(pso::DescriptorSetWrite {
+            set: descriptor_set,
+            binding,
+            array_offset: 0,
+            descriptors: iter::once(pso::Descriptor::CombinedImageSampler(
+                view,
+                ImageLayout::ShaderReadOnlyOptimal,
+                sampler,
+            )),
+        }));
+    }
+}
+
+/// Compiles `shader_source` to SPIR-V, reusing a cached `.spv` when one is already on disk.
+fn compile_fragment_shader(
+    compiler: &mut shaderc::Compiler,
+    device_key: &str,
+    shader_path: &str,
+    shader_source: &str,
+    stereo: bool,
+) -> Result<Vec<u32>> {
+    let shader_source = if stereo {
+        inject_shadertoy_uniforms_stereo(shader_source)
+    } else {
+        inject_shadertoy_uniforms(shader_source)
+    };
+    let hash = shader_content_hash(&shader_source, device_key);
+    let spirv_path = shader_cache_dir().join(format!("{}.spv", hash));
+
+    if let Ok(file) = std::fs::File::open(&spirv_path) {
+        return Ok(read_spirv(file).unwrap());
+    }
+
+    let artifact = compiler
+        .compile_into_spirv(&shader_source, shaderc::ShaderKind::Fragment, shader_path, "main", None)
+        .map_err(|error| format!("glsl->spv error: {}", error))?;
+
+    if std::fs::create_dir_all(shader_cache_dir()).is_ok() {
+        let _ = std::fs::write(&spirv_path, artifact.as_binary_u8());
+    }
+
+    Ok(artifact.as_binary().to_vec())
+}
+
+/// Builds the fullscreen-triangle graphics pipeline shared by every Shadertoy pass.
+fn build_fullscreen_pipeline<B: gfx_hal::Backend>(
+    device: &B::Device,
+    vertex_shader_module: &B::ShaderModule,
+    frag_shader_module: &B::ShaderModule,
+    render_pass: &B::RenderPass,
+    pipeline_layout: &B::PipelineLayout,
+    pipeline_cache: &B::PipelineCache,
+) -> Result<B::GraphicsPipeline> {
+    let (vs_entry, fs_entry) = (
+        pso::EntryPoint {
+            entry: "main",
+            module: vertex_shader_module,
+            specialization: pso::Specialization::default(),
+        },
+        pso::EntryPoint {
+            entry: "main",
+            module: frag_shader_module,
+            specialization: pso::Specialization::default(),
+        },
+    );
+
+    let subpass = Subpass {
+        index: 0,
+        main_pass: render_pass,
+    };
+
+    let pipeline_desc = pso::GraphicsPipelineDesc::new(
+        pso::PrimitiveAssemblerDesc::Vertex {
+            buffers: &[],
+            attributes: &[],
+            input_assembler: pso::InputAssemblerDesc {
+                primitive: pso::Primitive::TriangleList,
+                with_adjacency: false,
+                restart_index: None,
+            },
+            vertex: vs_entry,
+            geometry: None,
+            tessellation: None,
+        },
+        pso::Rasterizer::FILL,
+        Some(fs_entry),
+        pipeline_layout,
+        subpass,
+    );
+
+    unsafe { device.create_graphics_pipeline(&pipeline_desc, Some(pipeline_cache)) }
+        .map_err(|error| format!("pipeline creation failed: {}", error).into())
+}
+
+/// Mirrors Shadertoy's built-in fragment uniforms; uploaded as a push-constant block each frame.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct ShadertoyUniforms {
+    resolution: [f32; 3],
+    time: f32,
+    time_delta: f32,
+    frame: i32,
+    current_eye: i32,
+    _pad: f32,
+    mouse: [f32; 4],
+    date: [f32; 4],
+    eye_offsets: [[f32; 4]; 2],
+}
+
+/// GLSL declarations for the uniforms in [`ShadertoyUniforms`].
+const SHADERTOY_UNIFORMS_GLSL: &str = "\
+layout(push_constant) uniform ShadertoyUniforms {
+    vec3 iResolution;
+    float iTime;
+    float iTimeDelta;
+    int iFrame;
+    int iCurrentEye;
+    vec4 iMouse;
+    vec4 iDate;
+    vec4 iEyeOffset[2];
+};
+";
+
+/// Like [`SHADERTOY_UNIFORMS_GLSL`], but also defines `iEye` as an alias for `iEyeOffset[iCurrentEye]`.
+const SHADERTOY_UNIFORMS_GLSL_STEREO: &str = "\
+layout(push_constant) uniform ShadertoyUniforms {
+    vec3 iResolution;
+    float iTime;
+    float iTimeDelta;
+    int iFrame;
+    int iCurrentEye;
+    vec4 iMouse;
+    vec4 iDate;
+    vec4 iEyeOffset[2];
+};
+#define iEye iEyeOffset[iCurrentEye]
+";
+
+/// Inserts `header` right after the `#version` directive (GLSL requires `#version` to be the
+/// first line of the file), or at the top if the source has none.
+fn inject_glsl_header(source: &str, header: &str) -> String {
+    match source.find('\n').filter(|_| source.trim_start().starts_with("#version")) {
+        Some(newline) => {
+            let (version_line, rest) = source.split_at(newline + 1);
+            format!("{}{}{}", version_line, header, rest)
+        }
+        None => format!("{}{}", header, source),
+    }
+}
+
+/// Prepends the mono-rendering Shadertoy uniform block (see [`SHADERTOY_UNIFORMS_GLSL`]).
+fn inject_shadertoy_uniforms(source: &str) -> String {
+    inject_glsl_header(source, SHADERTOY_UNIFORMS_GLSL)
+}
+
+/// Prepends the stereo-rendering Shadertoy uniform block (see [`SHADERTOY_UNIFORMS_GLSL_STEREO`]).
+fn inject_shadertoy_uniforms_stereo(source: &str) -> String {
+    inject_glsl_header(source, SHADERTOY_UNIFORMS_GLSL_STEREO)
+}
+
+/// Days-since-epoch to (year, month, day), via Howard Hinnant's `civil_from_days`.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+/// Computes Shadertoy's `iDate` uniform from the system clock.
+fn shadertoy_date() -> [f32; 4] {
+    let since_epoch = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let days = (since_epoch.as_secs() / 86400) as i64;
+    let seconds_today = since_epoch.as_secs() % 86400;
+    let (year, month, day) = civil_from_days(days);
+    [
+        year as f32,
+        month as f32,
+        day as f32,
+        seconds_today as f32 + since_epoch.subsec_nanos() as f32 / 1_000_000_000.0,
+    ]
+}
+
+/// Builds the per-draw push-constant block from `params`.
+fn shadertoy_uniforms(
+    dimensions: Extent2D,
+    params: &RenderParams<'_>,
+    current_eye: i32,
+    eye_offsets: [[f32; 4]; 2],
+) -> ShadertoyUniforms {
+    let mouse = &params.mouse;
+    ShadertoyUniforms {
+        resolution: [dimensions.width as f32, dimensions.height as f32, 1.0],
+        time: params.elapsed.as_secs_f32(),
+        time_delta: params.delta.as_secs_f32(),
+        frame: params.frame as i32,
+        current_eye,
+        _pad: 0.0,
+        mouse: [
+            mouse.x,
+            mouse.y,
+            if mouse.pressed { mouse.click_x } else { -mouse.click_x },
+            if mouse.pressed { mouse.click_y } else { -mouse.click_y },
+        ],
+        date: shadertoy_date(),
+        eye_offsets,
+    }
+}
+
 pub struct GfxBackend<B: gfx_hal::Backend> {
     adapter: Adapter<B>,
     instance: B::Instance,
@@ -29,22 +707,164 @@ pub struct GfxBackend<B: gfx_hal::Backend> {
     render_pass: B::RenderPass,
     pipeline_layout: B::PipelineLayout,
     pipeline_cache: B::PipelineCache,
+    command_pool: B::CommandPool,
+    command_buffer: B::CommandBuffer,
+    submission_complete_fence: B::Fence,
+    rendering_complete_semaphore: B::Semaphore,
+    framebuffer: B::Framebuffer,
+    display_format: Format,
+    dimensions: Extent2D,
+    /// Adapter this backend's pipeline cache entries were built against; see `device_cache_key`.
+    device_cache_key: String,
+    channel_descriptor_set_layout: B::DescriptorSetLayout,
+    channel_descriptor_pool: Mutex<B::DescriptorPool>,
+    /// One iChannel descriptor set per pipeline, indexed in lockstep with `pipelines`.
+    channel_descriptor_sets: Mutex<Vec<B::DescriptorSet>>,
+    /// Index 0 is always the black placeholder bound to unset channels.
+    channel_textures: Mutex<Vec<GfxTexture<B>>>,
+    /// Offscreen Buffer A-D passes, executed in index order before the final Image pass.
+    buffer_passes: Mutex<Vec<BufferPass<B>>>,
+    /// Sampler used to read buffer-pass outputs back as `iChannel` inputs.
+    buffer_pass_sampler: B::Sampler,
+    /// `iChannel` bindings rebound every frame from a live buffer-pass output, keyed by pipeline.
+    dynamic_channels: Mutex<HashMap<RenderPipelineHandle, [Option<ChannelSource>; CHANNEL_COUNT]>>,
+    /// Index into `channel_textures` bound to a given `(pipeline, channel)` slot, so a
+    /// replacement can destroy the texture it displaces instead of leaking it.
+    channel_texture_slots: Mutex<HashMap<(RenderPipelineHandle, u32), usize>>,
+    output_mode: OutputMode,
+    /// `(shader_path, shader_source)` per pipeline, indexed in lockstep with `pipelines`.
+    pipeline_sources: Mutex<Vec<(String, String)>>,
+    /// Stereo variants of `pipelines`, compiled lazily on first stereo render.
+    stereo_pipelines: HashMap<RenderPipelineHandle, B::GraphicsPipeline>,
+    /// Resources for the current stereo output target; `None` in `OutputMode::Mono`.
+    stereo: Option<StereoState<B>>,
 }
 
 impl<B: gfx_hal::Backend> GfxBackend<B> {
     pub fn new(window: &winit::window::Window) -> Self {
+        Self::with_adapter_preference(window, AdapterPreference::default())
+    }
+
+    /// Like [`new`](Self::new), but picks the adapter to open according to `preference`.
+    pub fn with_adapter_preference(window: &winit::window::Window, preference: AdapterPreference) -> Self {
         let instance = B::Instance::create("shadertoy-browser", 1).unwrap();
+        let surface = unsafe { instance.create_surface(window) }.unwrap();
+        let adapters = instance.enumerate_adapters();
+        for adapter in &adapters {
+            println!("{:?}", adapter.info);
+        }
+        let adapter = select_adapter(adapters, &surface, &preference)
+            .expect("no adapter can present to this surface");
+        let window_size = window.inner_size();
 
-        let mut surface = unsafe { instance.create_surface(window) }.unwrap();
+        Self::build(instance, surface, adapter, window_size)
+    }
 
-        let adapter = {
-            let mut adapters = instance.enumerate_adapters();
-            for adapter in &adapters {
-                println!("{:?}", adapter.info);
+    /// Returns the `AdapterInfo` for every adapter the current instance enumerates.
+    pub fn available_adapters(&self) -> Vec<hal::adapter::AdapterInfo> {
+        self.instance
+            .enumerate_adapters()
+            .into_iter()
+            .map(|adapter| adapter.info)
+            .collect()
+    }
+
+    /// Returns the `AdapterInfo` of the adapter the device is currently opened on.
+    pub fn current_adapter(&self) -> &hal::adapter::AdapterInfo {
+        &self.adapter.info
+    }
+
+    /// Re-selects an adapter matching `preference` and rebuilds the device and swapchain
+    /// against it, reusing the existing `Instance`/`Surface`. This drops every pipeline,
+    /// buffer pass, channel texture, and stereo state the old device owned — none of it is
+    /// recompiled or reuploaded against the new device, so the caller must re-register
+    /// pipelines/buffer passes/textures from scratch afterwards. Any `RenderPipelineHandle`
+    /// or `BufferPassHandle` obtained before the switch is no longer valid; `render_frame`
+    /// ignores an out-of-range `RenderParams::pipeline` instead of panicking, but other
+    /// methods called with a stale handle may still return an error or panic.
+    pub fn switch_adapter(
+        self,
+        window: &winit::window::Window,
+        preference: AdapterPreference,
+    ) -> Result<Self> {
+        let adapters = self.instance.enumerate_adapters();
+        let window_size = window.inner_size();
+        let (instance, surface) = self.destroy();
+
+        let adapter = select_adapter(adapters, &surface, &preference)
+            .ok_or_else(|| "no adapter satisfies the requested preference and can present to this surface".to_string())?;
+
+        Ok(Self::build(instance, surface, adapter, window_size))
+    }
+
+    /// Explicitly destroys every gfx-hal resource `self` owns, merges the pipeline cache to
+    /// disk, and hands back the `Instance` and `Surface` for reuse against a freshly opened
+    /// device. `GfxBackend` has no `Drop` impl, so without this every `switch_adapter` call
+    /// would leak the whole previous device's GPU resources.
+    fn destroy(self) -> (B::Instance, B::Surface) {
+        self.write_pipeline_cache();
+
+        unsafe {
+            self.device.wait_idle().unwrap();
+
+            for pipeline in self.pipelines.into_inner().unwrap() {
+                self.device.destroy_graphics_pipeline(pipeline);
             }
-            adapters.remove(0)
-        };
+            for (_, pipeline) in self.stereo_pipelines {
+                self.device.destroy_graphics_pipeline(pipeline);
+            }
+            if let Some(stereo) = self.stereo {
+                self.device.destroy_framebuffer(stereo.framebuffer);
+                self.device.destroy_render_pass(stereo.render_pass);
+                let [left_view, right_view] = stereo.layer_views;
+                self.device.destroy_image_view(left_view);
+                self.device.destroy_image_view(right_view);
+                self.device.destroy_image(stereo.image);
+                self.device.free_memory(stereo.memory);
+                self.device.destroy_graphics_pipeline(stereo.resolve_pipeline);
+            }
+            for pass in self.buffer_passes.into_inner().unwrap() {
+                self.device.destroy_framebuffer(pass.framebuffer);
+                self.device.destroy_render_pass(pass.render_pass);
+                self.device.destroy_graphics_pipeline(pass.pipeline);
+                for target in pass.targets {
+                    self.device.destroy_image_view(target.view);
+                    self.device.destroy_image(target.image);
+                    self.device.free_memory(target.memory);
+                }
+            }
+            for texture in self.channel_textures.into_inner().unwrap() {
+                self.device.destroy_image_view(texture.view);
+                self.device.destroy_image(texture.image);
+                self.device.free_memory(texture.memory);
+                self.device.destroy_sampler(texture.sampler);
+            }
+            self.device.destroy_sampler(self.buffer_pass_sampler);
+            self.device
+                .destroy_descriptor_pool(self.channel_descriptor_pool.into_inner().unwrap());
+            self.device.destroy_descriptor_set_layout(self.channel_descriptor_set_layout);
+            self.device.destroy_framebuffer(self.framebuffer);
+            self.device.destroy_render_pass(self.render_pass);
+            self.device.destroy_pipeline_layout(self.pipeline_layout);
+            self.device.destroy_pipeline_cache(self.pipeline_cache);
+            self.device.destroy_shader_module(self.vertex_shader_module);
+            self.device.destroy_command_pool(self.command_pool);
+            self.device.destroy_fence(self.submission_complete_fence);
+            self.device.destroy_semaphore(self.rendering_complete_semaphore);
+
+            let mut surface = self.surface;
+            surface.unconfigure_swapchain(&self.device);
 
+            (self.instance, surface)
+        }
+    }
+
+    fn build(
+        instance: B::Instance,
+        mut surface: B::Surface,
+        adapter: Adapter<B>,
+        window_size: winit::dpi::PhysicalSize<u32>,
+    ) -> Self {
         let family = adapter
             .queue_families
             .iter()
@@ -94,8 +914,6 @@ impl<B: gfx_hal::Backend> GfxBackend<B> {
                 .unwrap_or(formats[0])
         });
 
-        let window_size = window.inner_size();
-
         let dimensions = Extent2D {
             width: window_size.width,
             height: window_size.height,
@@ -154,16 +972,79 @@ impl<B: gfx_hal::Backend> GfxBackend<B> {
         }
         .unwrap();
 
+        let channel_descriptor_set_layout = unsafe {
+            device.create_descriptor_set_layout(
+                (0..CHANNEL_COUNT as u32).map(|binding| pso::DescriptorSetLayoutBinding {
+                    binding,
+                    ty: pso::DescriptorType::Image {
+                        ty: pso::ImageDescriptorType::Sampled { with_sampler: true },
+                    },
+                    count: 1,
+                    stage_flags: pso::ShaderStageFlags::FRAGMENT,
+                    immutable_samplers: false,
+                }),
+                iter::empty(),
+            )
+        }
+        .unwrap();
+
+        let channel_descriptor_pool = unsafe {
+            device.create_descriptor_pool(
+                MAX_PIPELINES,
+                iter::once(pso::DescriptorRangeDesc {
+                    ty: pso::DescriptorType::Image {
+                        ty: pso::ImageDescriptorType::Sampled { with_sampler: true },
+                    },
+                    count: MAX_PIPELINES * CHANNEL_COUNT,
+                }),
+                pso::DescriptorPoolCreateFlags::empty(),
+            )
+        }
+        .unwrap();
+
         let pipeline_layout = unsafe {
-            device.create_pipeline_layout(iter::empty(), iter::empty())
+            device.create_pipeline_layout(
+                iter::once(&channel_descriptor_set_layout),
+                iter::once((
+                    pso::ShaderStageFlags::FRAGMENT,
+                    0..std::mem::size_of::<ShadertoyUniforms>() as u32,
+                )),
+            )
         }.unwrap();
 
-        let data = std::fs::read("happy_cache.cache").unwrap();
+        let device_cache_key = device_cache_key(&adapter.info);
+        let cached_pipeline_data = load_pipeline_cache_data(&device_cache_key);
 
         let pipeline_cache = unsafe {
-            device.create_pipeline_cache(Some(&data))
+            device.create_pipeline_cache(cached_pipeline_data.as_deref())
         }.unwrap();
 
+        let command_buffer = unsafe {
+            command_pool.allocate_one(gfx_hal::command::Level::Primary)
+        };
+
+        let submission_complete_fence = device.create_fence(true).unwrap();
+        let rendering_complete_semaphore = device.create_semaphore().unwrap();
+
+        // Channel 0 of `channel_textures` is a 1x1 black placeholder bound to every
+        // iChannel slot until a real texture is uploaded for it.
+        let placeholder_texture = create_texture(
+            &device,
+            &adapter.physical_device,
+            &mut command_pool,
+            &mut queue_group.queues[0],
+            1,
+            1,
+            &[0, 0, 0, 255],
+            TextureWrap::Repeat,
+            TextureFilter::Linear,
+        );
+
+        let buffer_pass_sampler = unsafe {
+            device.create_sampler(&SamplerDesc::new(hal::image::Filter::Linear, hal::image::WrapMode::Clamp))
+        }
+        .unwrap();
+
         Self {
             adapter,
             instance,
@@ -175,41 +1056,395 @@ impl<B: gfx_hal::Backend> GfxBackend<B> {
             render_pass,
             pipeline_layout,
             pipeline_cache,
+            command_pool,
+            command_buffer,
+            submission_complete_fence,
+            rendering_complete_semaphore,
+            framebuffer,
+            display_format,
+            dimensions,
+            device_cache_key,
+            channel_descriptor_set_layout,
+            channel_descriptor_pool: Mutex::new(channel_descriptor_pool),
+            channel_descriptor_sets: Default::default(),
+            channel_textures: Mutex::new(vec![placeholder_texture]),
+            buffer_passes: Default::default(),
+            buffer_pass_sampler,
+            dynamic_channels: Default::default(),
+            channel_texture_slots: Default::default(),
+            output_mode: OutputMode::default(),
+            pipeline_sources: Default::default(),
+            stereo_pipelines: HashMap::new(),
+            stereo: None,
+        }
+    }
+
+    /// Re-queries the surface's capabilities and rebuilds the swapchain and its framebuffer
+    /// for `dimensions`. Called on resize and whenever `acquire_image`/`present` report the
+    /// swapchain is out of date.
+    fn recreate_swapchain(&mut self, dimensions: Extent2D) {
+        unsafe {
+            self.device.wait_idle().unwrap();
+        }
+
+        let caps = self.surface.capabilities(&self.adapter.physical_device);
+        let swap_config = SwapchainConfig::from_caps(&caps, self.display_format, dimensions);
+        let framebuffer_attachment = swap_config.framebuffer_attachment();
+        let extent = swap_config.extent;
+
+        unsafe {
+            self.surface
+                .configure_swapchain(&self.device, swap_config)
+                .expect("Can't configure swapchain");
+        }
+
+        let framebuffer = unsafe {
+            self.device.create_framebuffer(
+                &self.render_pass,
+                iter::once(framebuffer_attachment),
+                Extent3D {
+                    width: extent.width,
+                    height: extent.height,
+                    depth: 1,
+                },
+            )
+        }
+        .unwrap();
+
+        unsafe {
+            self.device.destroy_framebuffer(std::mem::replace(&mut self.framebuffer, framebuffer));
+        }
+
+        self.dimensions = dimensions;
+
+        // Buffer A-D targets track the output resolution too, so recreate both halves of
+        // each pass's ping-pong pair at the new size.
+        let mut buffer_passes = self.buffer_passes.lock().unwrap();
+        for pass in buffer_passes.iter_mut() {
+            if pass.extent == dimensions {
+                continue;
+            }
+            let new_targets = [
+                create_offscreen_target(&self.device, &self.adapter.physical_device, dimensions),
+                create_offscreen_target(&self.device, &self.adapter.physical_device, dimensions),
+            ];
+            let old_targets = std::mem::replace(&mut pass.targets, new_targets);
+            for target in old_targets {
+                unsafe {
+                    self.device.destroy_image_view(target.view);
+                    self.device.destroy_image(target.image);
+                    self.device.free_memory(target.memory);
+                }
+            }
+            pass.extent = dimensions;
+        }
+        drop(buffer_passes);
+
+        // The stereo target tracks the output resolution too; `set_output_mode` already
+        // rebuilds it lazily when `extent` is stale, so just drop the old one here and let
+        // the next `OutputMode::Stereo` frame (or an explicit `set_output_mode` call) recreate
+        // it at the new size instead of rendering into a mismatched framebuffer meanwhile.
+        if let Some(stereo) = self.stereo.take() {
+            if stereo.extent != dimensions {
+                unsafe {
+                    self.device.destroy_framebuffer(stereo.framebuffer);
+                    self.device.destroy_render_pass(stereo.render_pass);
+                    let [left_view, right_view] = stereo.layer_views;
+                    self.device.destroy_image_view(left_view);
+                    self.device.destroy_image_view(right_view);
+                    self.device.destroy_image(stereo.image);
+                    self.device.free_memory(stereo.memory);
+                    self.device.destroy_graphics_pipeline(stereo.resolve_pipeline);
+                }
+            } else {
+                self.stereo = Some(stereo);
+            }
         }
     }
 }
 
 impl<B: gfx_hal::Backend> RenderBackend for GfxBackend<B> {
     fn render_frame(&mut self, params: RenderParams<'_>) {
-        todo!()
-    }
+        let dimensions = Extent2D {
+            width: params.extent.width,
+            height: params.extent.height,
+        };
 
-    fn new_pipeline(&self, shader_path: &str, shader_source: &str) -> Result<RenderPipelineHandle> {
-        let mut compiler = shaderc::Compiler::new().unwrap();
+        if dimensions != self.dimensions {
+            self.recreate_swapchain(dimensions);
+        }
 
-        let spirv_path: std::path::PathBuf = format!("{}.spv", shader_path).into();
-
-        let spv = if spirv_path.exists() {
-            read_spirv(std::fs::File::open(spirv_path).unwrap()).unwrap()
-        } else {
-            let result = compiler.compile_into_spirv(
-                shader_source,
-                shaderc::ShaderKind::Fragment,
-                shader_path,
-                "main",
-                None
-            );
+        // `params.pipeline` can be stale after a `switch_adapter`, which drops every
+        // registered pipeline; skip the frame instead of panicking on an out-of-range index.
+        if params.pipeline >= self.pipelines.lock().unwrap().len() {
+            eprintln!("render_frame: pipeline handle {} is out of range, skipping frame", params.pipeline);
+            return;
+        }
 
-            let artifact = match result {
-                Ok(artifact) => artifact,
-                Err(error) => return Err(format!("glsl->spv error: {}", error).into())
-            };
+        self.render_buffer_passes(&params);
 
-            std::fs::write(spirv_path, artifact.as_binary_u8()).unwrap();
+        unsafe {
+            self.device
+                .wait_for_fence(&self.submission_complete_fence, !0)
+                .unwrap();
+        }
 
-            artifact.as_binary().to_vec()
+        // Don't reset the fence until we know we'll actually submit something to re-signal
+        // it: an `OutOfDate` acquire returns early with nothing queued, and a fence reset on
+        // that path would leave it unsignaled forever, hanging the next frame's
+        // `wait_for_fence` above.
+        // A `suboptimal` acquire still hands back a valid image from the *current* swapchain,
+        // so render and present it as-is this frame (the standard gfx-hal idiom); recreating
+        // the swapchain here instead, before presenting, would tear down and replace the very
+        // swapchain this image was acquired from out from under the in-flight present call.
+        // Just remember to recreate once this frame is out the door.
+        let mut needs_recreate = false;
+        let surface_image = match unsafe { self.surface.acquire_image(!0) } {
+            Ok((image, suboptimal)) => {
+                needs_recreate = suboptimal.is_some();
+                image
+            }
+            Err(hal::window::AcquireError::OutOfDate(_)) => {
+                self.recreate_swapchain(dimensions);
+                return;
+            }
+            Err(error) => panic!("acquire_image failed: {:?}", error),
         };
 
+        unsafe {
+            self.device
+                .reset_fence(&mut self.submission_complete_fence)
+                .unwrap();
+            self.command_pool.reset(false);
+        }
+
+        let viewport = pso::Viewport {
+            rect: pso::Rect {
+                x: 0,
+                y: 0,
+                w: dimensions.width as i16,
+                h: dimensions.height as i16,
+            },
+            depth: 0.0..1.0,
+        };
+
+        if let Some(channels) = self.dynamic_channels.lock().unwrap().get(&params.pipeline).copied() {
+            let mut descriptor_sets = self.channel_descriptor_sets.lock().unwrap();
+            for (channel, source) in channels.iter().enumerate() {
+                if let Some(source) = source {
+                    self.resolve_dynamic_channel(&mut descriptor_sets[params.pipeline], channel as u32, *source);
+                }
+            }
+        }
+
+        // Stereo drawing needs its own pipeline variant (compiled against the stereo render
+        // pass and the `iEye`-aware uniform block); fall back to mono for this frame only if
+        // that fails, rather than permanently flipping `output_mode` off a single bad frame.
+        let eye_separation = match self.output_mode {
+            OutputMode::Stereo { eye_separation } if self.stereo.is_some() => {
+                match self.ensure_stereo_pipeline(params.pipeline) {
+                    Ok(()) => Some(eye_separation),
+                    Err(error) => {
+                        eprintln!("stereo pipeline compile failed, rendering mono this frame: {}", error);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let pipelines = self.pipelines.lock().unwrap();
+        let pipeline = &pipelines[params.pipeline];
+        let descriptor_sets = self.channel_descriptor_sets.lock().unwrap();
+        let descriptor_set = &descriptor_sets[params.pipeline];
+
+        unsafe {
+            self.command_buffer
+                .begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
+
+            self.command_buffer.set_viewports(0, iter::once(viewport.clone()));
+            self.command_buffer.set_scissors(0, iter::once(viewport.rect));
+
+            if let Some(eye_separation) = eye_separation {
+                let stereo = self.stereo.as_ref().unwrap();
+                let stereo_pipeline = &self.stereo_pipelines[&params.pipeline];
+                let eye_offsets = [
+                    [-eye_separation * 0.5, 0.0, 0.0, 0.0],
+                    [eye_separation * 0.5, 0.0, 0.0, 0.0],
+                ];
+
+                for eye in 0..2 {
+                    self.command_buffer.begin_render_pass(
+                        &stereo.render_pass,
+                        &stereo.framebuffer,
+                        viewport.rect,
+                        iter::once(hal::command::RenderAttachmentInfo {
+                            image_view: &stereo.layer_views[eye],
+                            clear_value: hal::command::ClearValue {
+                                color: hal::command::ClearColor {
+                                    float32: [0.0, 0.0, 0.0, 1.0],
+                                },
+                            },
+                        }),
+                        hal::command::SubpassContents::Inline,
+                    );
+
+                    self.command_buffer.bind_graphics_pipeline(stereo_pipeline);
+                    self.command_buffer.bind_graphics_descriptor_sets(
+                        &self.pipeline_layout,
+                        0,
+                        iter::once(descriptor_set),
+                        iter::empty(),
+                    );
+
+                    let uniforms = shadertoy_uniforms(dimensions, &params, eye as i32, eye_offsets);
+                    let uniforms_bytes = std::slice::from_raw_parts(
+                        &uniforms as *const ShadertoyUniforms as *const u32,
+                        std::mem::size_of::<ShadertoyUniforms>() / std::mem::size_of::<u32>(),
+                    );
+                    self.command_buffer.push_graphics_constants(
+                        &self.pipeline_layout,
+                        pso::ShaderStageFlags::FRAGMENT,
+                        0,
+                        uniforms_bytes,
+                    );
+
+                    self.command_buffer.draw(0..3, 0..1);
+                    self.command_buffer.end_render_pass();
+                }
+
+                // The eye passes and the resolve pass are two separate render passes recorded
+                // into the same command buffer and submitted together; without this, nothing
+                // guarantees the resolve pass's sampled reads of `stereo.layer_views` happen
+                // after the eye passes' color writes land (the render pass's own final-layout
+                // transition reorders the image layout, but doesn't by itself establish the
+                // write-then-read memory dependency across two unrelated render passes). Mirrors
+                // the barrier style `create_texture` already uses for its upload-then-sample
+                // transition.
+                self.command_buffer.pipeline_barrier(
+                    pso::PipelineStage::COLOR_ATTACHMENT_OUTPUT..pso::PipelineStage::FRAGMENT_SHADER,
+                    memory::Dependencies::empty(),
+                    iter::once(memory::Barrier::Image {
+                        states: (hal::image::Access::COLOR_ATTACHMENT_WRITE, ImageLayout::ShaderReadOnlyOptimal)
+                            ..(hal::image::Access::SHADER_READ, ImageLayout::ShaderReadOnlyOptimal),
+                        target: &stereo.image,
+                        families: None,
+                        range: SubresourceRange {
+                            aspects: Aspects::COLOR,
+                            layer_start: 0,
+                            layer_count: Some(2),
+                            ..Default::default()
+                        },
+                    }),
+                );
+
+                self.command_buffer.begin_render_pass(
+                    &self.render_pass,
+                    &self.framebuffer,
+                    viewport.rect,
+                    iter::once(hal::command::RenderAttachmentInfo {
+                        image_view: std::borrow::Borrow::borrow(&surface_image),
+                        clear_value: hal::command::ClearValue {
+                            color: hal::command::ClearColor {
+                                float32: [0.0, 0.0, 0.0, 1.0],
+                            },
+                        },
+                    }),
+                    hal::command::SubpassContents::Inline,
+                );
+
+                self.command_buffer.bind_graphics_pipeline(&stereo.resolve_pipeline);
+                self.command_buffer.bind_graphics_descriptor_sets(
+                    &self.pipeline_layout,
+                    0,
+                    iter::once(&stereo.resolve_descriptor_set),
+                    iter::empty(),
+                );
+
+                let uniforms = shadertoy_uniforms(dimensions, &params, 0, [[0.0; 4]; 2]);
+                let uniforms_bytes = std::slice::from_raw_parts(
+                    &uniforms as *const ShadertoyUniforms as *const u32,
+                    std::mem::size_of::<ShadertoyUniforms>() / std::mem::size_of::<u32>(),
+                );
+                self.command_buffer.push_graphics_constants(
+                    &self.pipeline_layout,
+                    pso::ShaderStageFlags::FRAGMENT,
+                    0,
+                    uniforms_bytes,
+                );
+
+                self.command_buffer.draw(0..3, 0..1);
+                self.command_buffer.end_render_pass();
+            } else {
+                self.command_buffer.begin_render_pass(
+                    &self.render_pass,
+                    &self.framebuffer,
+                    viewport.rect,
+                    iter::once(hal::command::RenderAttachmentInfo {
+                        image_view: std::borrow::Borrow::borrow(&surface_image),
+                        clear_value: hal::command::ClearValue {
+                            color: hal::command::ClearColor {
+                                float32: [0.0, 0.0, 0.0, 1.0],
+                            },
+                        },
+                    }),
+                    hal::command::SubpassContents::Inline,
+                );
+
+                self.command_buffer.bind_graphics_pipeline(pipeline);
+                self.command_buffer.bind_graphics_descriptor_sets(
+                    &self.pipeline_layout,
+                    0,
+                    iter::once(descriptor_set),
+                    iter::empty(),
+                );
+
+                let uniforms = shadertoy_uniforms(dimensions, &params, 0, [[0.0; 4]; 2]);
+                let uniforms_bytes = std::slice::from_raw_parts(
+                    &uniforms as *const ShadertoyUniforms as *const u32,
+                    std::mem::size_of::<ShadertoyUniforms>() / std::mem::size_of::<u32>(),
+                );
+                self.command_buffer.push_graphics_constants(
+                    &self.pipeline_layout,
+                    pso::ShaderStageFlags::FRAGMENT,
+                    0,
+                    uniforms_bytes,
+                );
+
+                self.command_buffer.draw(0..3, 0..1);
+                self.command_buffer.end_render_pass();
+            }
+
+            self.command_buffer.finish();
+        }
+
+        drop(pipelines);
+        drop(descriptor_sets);
+
+        unsafe {
+            self.queue_group.queues[0].submit(
+                iter::once(&self.command_buffer),
+                iter::empty(),
+                iter::once(&self.rendering_complete_semaphore),
+                Some(&mut self.submission_complete_fence),
+            );
+
+            let result = self.queue_group.queues[0].present(
+                &mut self.surface,
+                surface_image,
+                Some(&mut self.rendering_complete_semaphore),
+            );
+
+            if result.is_err() || needs_recreate {
+                self.recreate_swapchain(dimensions);
+            }
+        }
+    }
+
+    fn new_pipeline(&self, shader_path: &str, shader_source: &str) -> Result<RenderPipelineHandle> {
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let spv = compile_fragment_shader(&mut compiler, &self.device_cache_key, shader_path, shader_source, false)?;
+
         let frag_shader_module = match unsafe {
             self.device.create_shader_module(&spv)
         } {
@@ -217,62 +1452,581 @@ impl<B: gfx_hal::Backend> RenderBackend for GfxBackend<B> {
             Err(error) => return Err(format!("create_shader_module error: {}", error).into())
         };
 
-        let (vs_entry, fs_entry) = (
-            pso::EntryPoint {
-                entry: "main",
-                module: &self.vertex_shader_module,
-                specialization: pso::Specialization::default(),
-            },
-            pso::EntryPoint {
-                entry: "main",
-                module: &frag_shader_module,
-                specialization: pso::Specialization::default(),
-            },
+        let pipeline = build_fullscreen_pipeline::<B>(
+            &self.device,
+            &self.vertex_shader_module,
+            &frag_shader_module,
+            &self.render_pass,
+            &self.pipeline_layout,
+            &self.pipeline_cache,
+        )?;
+
+        let mut descriptor_set = unsafe {
+            self.channel_descriptor_pool
+                .lock()
+                .unwrap()
+                .allocate_one(&self.channel_descriptor_set_layout)
+        }
+        .map_err(|error| format!("descriptor set allocation failed: {:?}", error))?;
+
+        // Bind the placeholder to every channel so the set is fully populated (and
+        // therefore legal to use) before any real iChannel texture is uploaded.
+        let channel_textures = self.channel_textures.lock().unwrap();
+        let placeholder = &channel_textures[0];
+        for channel in 0..CHANNEL_COUNT as u32 {
+            write_channel_descriptor(&self.device, &mut descriptor_set, channel, placeholder);
+        }
+        drop(channel_textures);
+
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let mut descriptor_sets = self.channel_descriptor_sets.lock().unwrap();
+
+        pipelines.push(pipeline);
+        descriptor_sets.push(descriptor_set);
+        // Kept alongside `pipelines` so a stereo variant can be compiled on demand (see
+        // `ensure_stereo_pipeline`) without the caller having to hand the source back in.
+        self.pipeline_sources
+            .lock()
+            .unwrap()
+            .push((shader_path.to_string(), shader_source.to_string()));
+
+        Ok(pipelines.len() - 1)
+    }
+
+    fn write_pipeline_cache(&self) {
+        let data = match unsafe { self.device.get_pipeline_cache_data(&self.pipeline_cache) } {
+            Ok(data) => data,
+            Err(_) => return,
+        };
+
+        if std::fs::create_dir_all(shader_cache_dir()).is_err() {
+            return;
+        }
+
+        // Write-then-rename so a crash or concurrent writer never leaves a half-written
+        // cache file for the next run to trip over.
+        let tmp_path = shader_cache_dir().join("pipeline.cache.tmp");
+        let data_path = shader_cache_dir().join("pipeline.cache");
+
+        if std::fs::write(&tmp_path, &data).is_ok() && std::fs::rename(&tmp_path, &data_path).is_ok() {
+            let _ = std::fs::write(shader_cache_dir().join("pipeline.cache.meta"), &self.device_cache_key);
+        }
+    }
+}
+
+impl<B: gfx_hal::Backend> GfxBackend<B> {
+    /// Decodes `image_bytes` (any format the `image` crate supports) and binds it as the
+    /// `iChannelN` input (`channel` in `0..4`) of the given pipeline, replacing whatever
+    /// was previously bound to that slot.
+    pub fn upload_channel_texture(
+        &mut self,
+        pipeline: RenderPipelineHandle,
+        channel: u32,
+        image_bytes: &[u8],
+        wrap: TextureWrap,
+        filter: TextureFilter,
+    ) -> Result<()> {
+        if pipeline >= self.channel_descriptor_sets.lock().unwrap().len() {
+            return Err(format!("pipeline handle {} is out of range", pipeline).into());
+        }
+
+        let decoded = image::load_from_memory(image_bytes)
+            .map_err(|error| format!("image decode error: {}", error))?
+            .to_rgba8();
+        let (width, height) = decoded.dimensions();
+
+        let texture = create_texture(
+            &self.device,
+            &self.adapter.physical_device,
+            &mut self.command_pool,
+            &mut self.queue_group.queues[0],
+            width,
+            height,
+            &decoded,
+            wrap,
+            filter,
         );
 
-        let subpass = Subpass {
-            index: 0,
-            main_pass: &self.render_pass,
+        write_channel_descriptor(
+            &self.device,
+            &mut self.channel_descriptor_sets.lock().unwrap()[pipeline],
+            channel,
+            &texture,
+        );
+
+        // Reuse this (pipeline, channel)'s existing slot in `channel_textures` if it already
+        // had an upload, destroying the texture it displaces instead of leaking it; otherwise
+        // this is the first upload for the slot, so append and remember the new index.
+        let mut slots = self.channel_texture_slots.lock().unwrap();
+        let mut channel_textures = self.channel_textures.lock().unwrap();
+        match slots.entry((pipeline, channel)) {
+            std::collections::hash_map::Entry::Occupied(entry) => {
+                let old_texture = std::mem::replace(&mut channel_textures[*entry.get()], texture);
+                unsafe {
+                    self.device.destroy_image_view(old_texture.view);
+                    self.device.destroy_image(old_texture.image);
+                    self.device.free_memory(old_texture.memory);
+                    self.device.destroy_sampler(old_texture.sampler);
+                }
+            }
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                channel_textures.push(texture);
+                entry.insert(channel_textures.len() - 1);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds an offscreen Buffer A-D pass rendering `shader_source` at `extent`, returning a
+    /// handle other passes (or the final Image pipeline) can bind as an `iChannel` source
+    /// via `set_buffer_pass_channel`/`set_pipeline_channel`.
+    pub fn add_buffer_pass(
+        &mut self,
+        shader_path: &str,
+        shader_source: &str,
+        extent: Extent2D,
+    ) -> Result<BufferPassHandle> {
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let spv = compile_fragment_shader(&mut compiler, &self.device_cache_key, shader_path, shader_source, false)?;
+
+        let frag_shader_module = match unsafe { self.device.create_shader_module(&spv) } {
+            Ok(module) => module,
+            Err(error) => return Err(format!("create_shader_module error: {}", error).into()),
         };
 
-        let pipeline_desc = pso::GraphicsPipelineDesc::new(
-            pso::PrimitiveAssemblerDesc::Vertex {
-                buffers: &[],
-                attributes: &[],
-                input_assembler: pso::InputAssemblerDesc {
-                    primitive: pso::Primitive::TriangleList,
-                        with_adjacency: false,
-                        restart_index: None,
-                },
-                vertex: vs_entry,
-                geometry: None,
-                tessellation: None,
-            },
-            pso::Rasterizer::FILL,
-            Some(fs_entry),
+        let render_pass = {
+            let attachment = pass::Attachment {
+                format: Some(OFFSCREEN_FORMAT),
+                samples: 1,
+                ops: pass::AttachmentOps::new(
+                    pass::AttachmentLoadOp::Clear,
+                    pass::AttachmentStoreOp::Store,
+                ),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: ImageLayout::Undefined..ImageLayout::ShaderReadOnlyOptimal,
+            };
+
+            let subpass = pass::SubpassDesc {
+                colors: &[(0, ImageLayout::ColorAttachmentOptimal)],
+                depth_stencil: None,
+                inputs: &[],
+                resolves: &[],
+                preserves: &[],
+            };
+
+            unsafe {
+                self.device
+                    .create_render_pass(iter::once(attachment), iter::once(subpass), iter::empty())
+            }
+            .unwrap()
+        };
+
+        let framebuffer_attachment = hal::image::FramebufferAttachment {
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+            view_caps: hal::image::ViewCapabilities::empty(),
+            format: OFFSCREEN_FORMAT,
+        };
+        let framebuffer = unsafe {
+            self.device.create_framebuffer(
+                &render_pass,
+                iter::once(framebuffer_attachment),
+                Extent3D { width: extent.width, height: extent.height, depth: 1 },
+            )
+        }
+        .unwrap();
+
+        let pipeline = build_fullscreen_pipeline::<B>(
+            &self.device,
+            &self.vertex_shader_module,
+            &frag_shader_module,
+            &render_pass,
             &self.pipeline_layout,
-            subpass
-        );
+            &self.pipeline_cache,
+        )?;
 
-        let pipeline = match unsafe {
-            self.device.create_graphics_pipeline(&pipeline_desc, Some(&self.pipeline_cache))
-        } {
-            Ok(pipeline) => pipeline,
-            Err(error) => return Err(format!("pipeline creation failed: {}", error).into())
+        let mut descriptor_set = unsafe {
+            self.channel_descriptor_pool
+                .lock()
+                .unwrap()
+                .allocate_one(&self.channel_descriptor_set_layout)
+        }
+        .map_err(|error| format!("descriptor set allocation failed: {:?}", error))?;
+
+        let channel_textures = self.channel_textures.lock().unwrap();
+        let placeholder = &channel_textures[0];
+        for channel in 0..CHANNEL_COUNT as u32 {
+            write_channel_descriptor(&self.device, &mut descriptor_set, channel, placeholder);
+        }
+        drop(channel_textures);
+
+        let targets = [
+            create_offscreen_target(&self.device, &self.adapter.physical_device, extent),
+            create_offscreen_target(&self.device, &self.adapter.physical_device, extent),
+        ];
+
+        let mut buffer_passes = self.buffer_passes.lock().unwrap();
+        buffer_passes.push(BufferPass {
+            render_pass,
+            framebuffer,
+            pipeline,
+            descriptor_set,
+            targets,
+            write_index: 0,
+            channels: [None; CHANNEL_COUNT],
+            extent,
+        });
+
+        Ok(buffer_passes.len() - 1)
+    }
+
+    /// Binds `source` as the `iChannelN` (`channel` in `0..4`) input of `pass`, re-resolved
+    /// every frame (so a `ChannelSource::BufferPass` dependency always sees the latest
+    /// output, including this pass's own previous frame for feedback).
+    pub fn set_buffer_pass_channel(&mut self, pass: BufferPassHandle, channel: u32, source: ChannelSource) -> Result<()> {
+        let mut buffer_passes = self.buffer_passes.lock().unwrap();
+        let pass = buffer_passes
+            .get_mut(pass)
+            .ok_or_else(|| format!("buffer pass handle {} is out of range", pass))?;
+        pass.channels[channel as usize] = Some(source);
+        Ok(())
+    }
+
+    /// Binds `source` as the `iChannelN` input of the final Image pipeline (the one passed
+    /// as `RenderParams::pipeline`), re-resolved every frame like `set_buffer_pass_channel`.
+    pub fn set_pipeline_channel(&mut self, pipeline: RenderPipelineHandle, channel: u32, source: ChannelSource) -> Result<()> {
+        if pipeline >= self.pipelines.lock().unwrap().len() {
+            return Err(format!("pipeline handle {} is out of range", pipeline).into());
+        }
+        let mut dynamic_channels = self.dynamic_channels.lock().unwrap();
+        let entry = dynamic_channels.entry(pipeline).or_insert([None; CHANNEL_COUNT]);
+        entry[channel as usize] = Some(source);
+        Ok(())
+    }
+
+    /// Switches between mono and stereo output, using two ordinary per-eye draws into a
+    /// layered offscreen target plus a resolve pass rather than hardware multiview.
+    pub fn set_output_mode(&mut self, mode: OutputMode) -> Result<()> {
+        match mode {
+            OutputMode::Mono => {
+                self.output_mode = OutputMode::Mono;
+            }
+            OutputMode::Stereo { eye_separation } => {
+                if self.stereo.as_ref().map_or(true, |stereo| stereo.extent != self.dimensions) {
+                    self.stereo = Some(self.build_stereo_state(self.dimensions)?);
+                }
+                self.output_mode = OutputMode::Stereo { eye_separation };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds the stereo render target: a 2-array-layer `OFFSCREEN_FORMAT` image (one layer
+    /// per eye) plus the resolve pipeline that composites both eyes into the swapchain image.
+    fn build_stereo_state(&mut self, extent: Extent2D) -> Result<StereoState<B>> {
+        if let Some(stereo) = self.stereo.take() {
+            unsafe {
+                self.device.destroy_framebuffer(stereo.framebuffer);
+                self.device.destroy_render_pass(stereo.render_pass);
+                let [left_view, right_view] = stereo.layer_views;
+                self.device.destroy_image_view(left_view);
+                self.device.destroy_image_view(right_view);
+                self.device.destroy_image(stereo.image);
+                self.device.free_memory(stereo.memory);
+                self.device.destroy_graphics_pipeline(stereo.resolve_pipeline);
+            }
+        }
+
+        let mut image = unsafe {
+            self.device.create_image(
+                ImageKind::D2(extent.width, extent.height, 2, 1),
+                1,
+                OFFSCREEN_FORMAT,
+                ImageTiling::Optimal,
+                ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+                memory::SparseFlags::empty(),
+                hal::image::ViewCapabilities::empty(),
+            )
+        }
+        .unwrap();
+
+        let image_req = unsafe { self.device.get_image_requirements(&image) };
+        let memory_type = self
+            .adapter
+            .physical_device
+            .memory_properties()
+            .memory_types
+            .iter()
+            .enumerate()
+            .position(|(id, mem_type)| {
+                image_req.type_mask & (1 << id) != 0
+                    && mem_type.properties.contains(memory::Properties::DEVICE_LOCAL)
+            })
+            .unwrap()
+            .into();
+        let memory = unsafe { self.device.allocate_memory(memory_type, image_req.size) }.unwrap();
+        unsafe { self.device.bind_image_memory(&memory, 0, &mut image) }.unwrap();
+
+        let layer_views = [
+            unsafe {
+                self.device.create_image_view(
+                    &image,
+                    ViewKind::D2,
+                    OFFSCREEN_FORMAT,
+                    Swizzle::NO_SWIZZLE,
+                    ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+                    SubresourceRange { aspects: Aspects::COLOR, layer_start: 0, layer_count: Some(1), ..Default::default() },
+                )
+            }
+            .unwrap(),
+            unsafe {
+                self.device.create_image_view(
+                    &image,
+                    ViewKind::D2,
+                    OFFSCREEN_FORMAT,
+                    Swizzle::NO_SWIZZLE,
+                    ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+                    SubresourceRange { aspects: Aspects::COLOR, layer_start: 1, layer_count: Some(1), ..Default::default() },
+                )
+            }
+            .unwrap(),
+        ];
+
+        let render_pass = {
+            let attachment = pass::Attachment {
+                format: Some(OFFSCREEN_FORMAT),
+                samples: 1,
+                ops: pass::AttachmentOps::new(pass::AttachmentLoadOp::Clear, pass::AttachmentStoreOp::Store),
+                stencil_ops: pass::AttachmentOps::DONT_CARE,
+                layouts: ImageLayout::Undefined..ImageLayout::ShaderReadOnlyOptimal,
+            };
+
+            let subpass = pass::SubpassDesc {
+                colors: &[(0, ImageLayout::ColorAttachmentOptimal)],
+                depth_stencil: None,
+                inputs: &[],
+                resolves: &[],
+                preserves: &[],
+            };
+
+            unsafe { self.device.create_render_pass(iter::once(attachment), iter::once(subpass), iter::empty()) }
+                .unwrap()
         };
 
-        let mut pipelines = self.pipelines.lock().unwrap();
+        let framebuffer_attachment = hal::image::FramebufferAttachment {
+            usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::SAMPLED,
+            view_caps: hal::image::ViewCapabilities::empty(),
+            format: OFFSCREEN_FORMAT,
+        };
+        let framebuffer = unsafe {
+            self.device.create_framebuffer(
+                &render_pass,
+                iter::once(framebuffer_attachment),
+                Extent3D { width: extent.width, height: extent.height, depth: 1 },
+            )
+        }
+        .unwrap();
 
-        pipelines.push(pipeline);
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let spv = compile_fragment_shader(
+            &mut compiler,
+            &self.device_cache_key,
+            "stereo_resolve.frag",
+            STEREO_RESOLVE_SHADER,
+            false,
+        )?;
+        let resolve_shader_module = unsafe { self.device.create_shader_module(&spv) }
+            .map_err(|error| format!("create_shader_module error: {}", error))?;
 
-        Ok(pipelines.len() - 1)
+        let resolve_pipeline = build_fullscreen_pipeline::<B>(
+            &self.device,
+            &self.vertex_shader_module,
+            &resolve_shader_module,
+            &self.render_pass,
+            &self.pipeline_layout,
+            &self.pipeline_cache,
+        )?;
+
+        let mut resolve_descriptor_set = unsafe {
+            self.channel_descriptor_pool.lock().unwrap().allocate_one(&self.channel_descriptor_set_layout)
+        }
+        .map_err(|error| format!("descriptor set allocation failed: {:?}", error))?;
+
+        let channel_textures = self.channel_textures.lock().unwrap();
+        let placeholder = &channel_textures[0];
+        for channel in 0..CHANNEL_COUNT as u32 {
+            write_channel_descriptor(&self.device, &mut resolve_descriptor_set, channel, placeholder);
+        }
+        drop(channel_textures);
+
+        write_descriptor_set_image(&self.device, &mut resolve_descriptor_set, 0, &layer_views[0], &self.buffer_pass_sampler);
+        write_descriptor_set_image(&self.device, &mut resolve_descriptor_set, 1, &layer_views[1], &self.buffer_pass_sampler);
+
+        Ok(StereoState {
+            render_pass,
+            framebuffer,
+            image,
+            memory,
+            layer_views,
+            resolve_pipeline,
+            resolve_descriptor_set,
+            extent,
+        })
     }
 
-    fn write_pipeline_cache(&self) {
-        let data = unsafe {
-            self.device.get_pipeline_cache_data(&self.pipeline_cache)
-        }.unwrap();
+    /// Compiles and caches the stereo variant of `pipeline` the first time it's drawn under
+    /// `OutputMode::Stereo`; a no-op once `stereo_pipelines` already has an entry.
+    fn ensure_stereo_pipeline(&mut self, pipeline: RenderPipelineHandle) -> Result<()> {
+        if self.stereo_pipelines.contains_key(&pipeline) {
+            return Ok(());
+        }
+
+        let (shader_path, shader_source) = self.pipeline_sources.lock().unwrap()[pipeline].clone();
+        let mut compiler = shaderc::Compiler::new().unwrap();
+        let spv = compile_fragment_shader(&mut compiler, &self.device_cache_key, &shader_path, &shader_source, true)?;
+        let frag_shader_module = unsafe { self.device.create_shader_module(&spv) }
+            .map_err(|error| format!("create_shader_module error: {}", error))?;
+
+        let stereo_render_pass = &self.stereo.as_ref().expect("stereo state built by set_output_mode").render_pass;
+        let stereo_pipeline = build_fullscreen_pipeline::<B>(
+            &self.device,
+            &self.vertex_shader_module,
+            &frag_shader_module,
+            stereo_render_pass,
+            &self.pipeline_layout,
+            &self.pipeline_cache,
+        )?;
+
+        self.stereo_pipelines.insert(pipeline, stereo_pipeline);
+        Ok(())
+    }
+
+    /// Looks up what `source` currently points at and writes it into `descriptor_set`.
+    fn resolve_dynamic_channel(&self, descriptor_set: &mut B::DescriptorSet, channel: u32, source: ChannelSource) {
+        match source {
+            ChannelSource::Texture(handle) => {
+                let channel_textures = self.channel_textures.lock().unwrap();
+                write_channel_descriptor(&self.device, descriptor_set, channel, &channel_textures[handle]);
+            }
+            ChannelSource::BufferPass(pass) => {
+                let buffer_passes = self.buffer_passes.lock().unwrap();
+                let pass = &buffer_passes[pass];
+                let view = &pass.targets[pass.read_index()].view;
+                write_descriptor_set_image(&self.device, descriptor_set, channel, view, &self.buffer_pass_sampler);
+            }
+        }
+    }
+
+    /// Runs every buffer pass in index order into its offscreen targets, ping-ponging so
+    /// each pass's next frame can read this frame's output.
+    fn render_buffer_passes(&mut self, params: &RenderParams<'_>) {
+        let mut buffer_passes = self.buffer_passes.lock().unwrap();
+        let pass_count = buffer_passes.len();
+
+        for index in 0..pass_count {
+            let channels = buffer_passes[index].channels;
+            for (channel, source) in channels.iter().enumerate() {
+                let channel = channel as u32;
+                match source {
+                    None => {}
+                    Some(ChannelSource::Texture(handle)) => {
+                        let channel_textures = self.channel_textures.lock().unwrap();
+                        write_channel_descriptor(
+                            &self.device,
+                            &mut buffer_passes[index].descriptor_set,
+                            channel,
+                            &channel_textures[*handle],
+                        );
+                    }
+                    // A dependency is always either this same pass (feedback, reading last
+                    // frame's output) or an earlier one (already re-pointed at its own
+                    // read target above this frame), so `split_at_mut` gives disjoint
+                    // borrows in both cases.
+                    Some(ChannelSource::BufferPass(dep)) if *dep == index => {
+                        let pass = &mut buffer_passes[index];
+                        let read_index = pass.read_index();
+                        let view = &pass.targets[read_index].view;
+                        write_descriptor_set_image(&self.device, &mut pass.descriptor_set, channel, view, &self.buffer_pass_sampler);
+                    }
+                    Some(ChannelSource::BufferPass(dep)) => {
+                        let (dep_passes, idx_passes) = buffer_passes.split_at_mut(index);
+                        let dep_pass = &dep_passes[*dep];
+                        let idx_pass = &mut idx_passes[0];
+                        let view = &dep_pass.targets[dep_pass.read_index()].view;
+                        write_descriptor_set_image(&self.device, &mut idx_pass.descriptor_set, channel, view, &self.buffer_pass_sampler);
+                    }
+                }
+            }
+
+            // The command buffer and fence are shared with every other pass (including the
+            // final Image pass) this frame, so wait for whatever used them last.
+            unsafe {
+                self.device.wait_for_fence(&self.submission_complete_fence, !0).unwrap();
+                self.device.reset_fence(&mut self.submission_complete_fence).unwrap();
+                self.command_pool.reset(false);
+            }
+
+            let pass = &buffer_passes[index];
+            let extent = pass.extent;
+            let write_view = &pass.targets[pass.write_index].view;
 
-        //std::fs::write("happy_cache.cache", data).unwrap();
+            let viewport = pso::Viewport {
+                rect: pso::Rect { x: 0, y: 0, w: extent.width as i16, h: extent.height as i16 },
+                depth: 0.0..1.0,
+            };
+
+            unsafe {
+                self.command_buffer.begin_primary(hal::command::CommandBufferFlags::ONE_TIME_SUBMIT);
+                self.command_buffer.set_viewports(0, iter::once(viewport.clone()));
+                self.command_buffer.set_scissors(0, iter::once(viewport.rect));
+                self.command_buffer.begin_render_pass(
+                    &pass.render_pass,
+                    &pass.framebuffer,
+                    viewport.rect,
+                    iter::once(hal::command::RenderAttachmentInfo {
+                        image_view: write_view,
+                        clear_value: hal::command::ClearValue {
+                            color: hal::command::ClearColor { float32: [0.0, 0.0, 0.0, 1.0] },
+                        },
+                    }),
+                    hal::command::SubpassContents::Inline,
+                );
+                self.command_buffer.bind_graphics_pipeline(&pass.pipeline);
+                self.command_buffer.bind_graphics_descriptor_sets(
+                    &self.pipeline_layout,
+                    0,
+                    iter::once(&pass.descriptor_set),
+                    iter::empty(),
+                );
+
+                let uniforms = shadertoy_uniforms(extent, params, 0, [[0.0; 4]; 2]);
+                let uniforms_bytes = std::slice::from_raw_parts(
+                    &uniforms as *const ShadertoyUniforms as *const u32,
+                    std::mem::size_of::<ShadertoyUniforms>() / std::mem::size_of::<u32>(),
+                );
+                self.command_buffer.push_graphics_constants(
+                    &self.pipeline_layout,
+                    pso::ShaderStageFlags::FRAGMENT,
+                    0,
+                    uniforms_bytes,
+                );
+
+                self.command_buffer.draw(0..3, 0..1);
+                self.command_buffer.end_render_pass();
+                self.command_buffer.finish();
+            }
+
+            unsafe {
+                self.queue_group.queues[0].submit(
+                    iter::once(&self.command_buffer),
+                    iter::empty(),
+                    iter::empty(),
+                    Some(&mut self.submission_complete_fence),
+                );
+            }
+
+            buffer_passes[index].write_index = 1 - buffer_passes[index].write_index;
+        }
     }
 }